@@ -9,7 +9,22 @@ use std::{
     ptr,
 };
 
-pub struct GpioChip(libc::c_int);
+pub struct GpioChip {
+    fd: libc::c_int,
+    /// Lazily registered with the `async-io` reactor the first time any pin
+    /// on this chip waits asynchronously, then reused for the chip's
+    /// lifetime — registering the same raw fd with the reactor twice fails
+    /// with `EEXIST`, and re-wrapping it on every call would also leave
+    /// `O_NONBLOCK` fighting with [`GpioPin::wait_for_edge`]'s blocking reads.
+    #[cfg(feature = "async")]
+    async_fd: std::sync::OnceLock<async_io::Async<ChipFd>>,
+    /// Serializes the first call to [`GpioChip::async_fd`]: two racing
+    /// first-time registrations would each construct an `Async<ChipFd>` for
+    /// the same raw fd, and dropping the losing one deregisters that fd from
+    /// the reactor out from under the winner too.
+    #[cfg(feature = "async")]
+    async_fd_init: std::sync::Mutex<()>,
+}
 pub struct GpioPins<'c> {
     chip: &'c GpioChip,
     base: *mut gpio_config_t,
@@ -30,17 +45,27 @@ pub struct GpioPin<'c, M> {
 
 impl std::os::unix::io::FromRawFd for GpioChip {
     unsafe fn from_raw_fd(fd: std::os::unix::io::RawFd) -> Self {
-        GpioChip(fd.into())
+        GpioChip::from_fd(fd)
     }
 }
 
 impl GpioChip {
+    fn from_fd(fd: libc::c_int) -> GpioChip {
+        GpioChip {
+            fd,
+            #[cfg(feature = "async")]
+            async_fd: std::sync::OnceLock::new(),
+            #[cfg(feature = "async")]
+            async_fd_init: std::sync::Mutex::new(()),
+        }
+    }
+
     pub fn from_unit(unit: u32) -> io::Result<GpioChip> {
         let res = unsafe { gpio_open(unit as _) };
         if res == -1 {
             return Err(io::Error::last_os_error());
         }
-        Ok(GpioChip(res))
+        Ok(GpioChip::from_fd(res))
     }
 
     pub fn from_path<P: AsRef<std::path::Path>>(path: P) -> io::Result<GpioChip> {
@@ -50,17 +75,40 @@ impl GpioChip {
         if res == -1 {
             return Err(io::Error::last_os_error());
         }
-        Ok(GpioChip(res))
+        Ok(GpioChip::from_fd(res))
     }
 
-    pub fn pins(&mut self) -> io::Result<GpioPins> {
+    pub fn pins(&mut self) -> io::Result<GpioPins<'_>> {
         let mut base = ptr::null_mut();
-        let pins = unsafe { gpio_pin_list(self.0, &mut base as _) };
+        let pins = unsafe { gpio_pin_list(self.fd, &mut base as _) };
         if pins == -1 {
             return Err(io::Error::last_os_error());
         }
         Ok(GpioPins { chip: self, base, offset: 0, max_pin: pins })
     }
+
+    /// Look up a single pin by its device/ACPI name, without the caller
+    /// holding the whole [`GpioPins`] allocation. Names are stable across
+    /// pin renumbering, which raw pin numbers aren't.
+    pub fn pin_by_name(&mut self, name: &str) -> io::Result<GpioPin<'_, Unknown>> {
+        self.pins()?
+            .find(|(n, _)| n == name)
+            .map(|(_, pin)| pin)
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, format!("no gpio pin named {:?}", name))
+            })
+    }
+
+    /// Look up a single pin by its number, without the caller holding the
+    /// whole [`GpioPins`] allocation.
+    pub fn pin_by_number(&mut self, num: u32) -> io::Result<GpioPin<'_, Unknown>> {
+        self.pins()?
+            .find(|(_, pin)| pin.num == num)
+            .map(|(_, pin)| pin)
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, format!("no gpio pin numbered {}", num))
+            })
+    }
 }
 
 impl<'c> Drop for GpioPins<'c> {
@@ -115,11 +163,11 @@ impl<'c, M> GpioPin<'c, M> {
     fn set_flags(&self, flags: u32) -> io::Result<()> {
         let mut pcfg =
             gpio_config_t { g_pin: self.num, g_name: [0; 64], g_caps: 0, g_flags: 0 };
-        if unsafe { gpio_pin_config(self.chip.0, &mut pcfg) } == -1 {
+        if unsafe { gpio_pin_config(self.chip.fd, &mut pcfg) } == -1 {
             return Err(io::Error::last_os_error());
         }
         pcfg.g_flags = flags;
-        if unsafe { gpio_pin_set_flags(self.chip.0, &mut pcfg) } == -1 {
+        if unsafe { gpio_pin_set_flags(self.chip.fd, &mut pcfg) } == -1 {
             return Err(io::Error::last_os_error());
         }
         Ok(())
@@ -194,7 +242,7 @@ impl<'c> embedded_hal::digital::blocking::InputPin for GpioPin<'c, Input> {
     type Error = io::Error;
 
     fn is_high(&self) -> Result<bool, Self::Error> {
-        let res = unsafe { gpio_pin_get(self.chip.0, self.num) };
+        let res = unsafe { gpio_pin_get(self.chip.fd, self.num) };
         if res == -1 {
             return Err(io::Error::last_os_error());
         }
@@ -206,11 +254,177 @@ impl<'c> embedded_hal::digital::blocking::InputPin for GpioPin<'c, Input> {
     }
 }
 
+/// A GPIO interrupt event, decoded from `struct gpio_event_summary`.
+#[derive(Debug, Clone, Copy)]
+pub struct GpioEdgeEvent {
+    pub pin: u32,
+    pub flags: u32,
+    pub first_time: std::time::Duration,
+    pub last_time: std::time::Duration,
+    pub count: u32,
+}
+
+/// Which edge(s) to arm a pin's interrupt for, see [`GpioPin::wait_for_edge`].
+pub enum Edge {
+    Rising,
+    Falling,
+    Both,
+}
+
+impl<'c> GpioPin<'c, Input> {
+    /// Arm the pin for edge interrupt delivery and block until a matching edge
+    /// is reported on the chip's fd, returning the decoded event.
+    ///
+    /// Don't mix this with the chip's async wait methods (e.g.
+    /// [`wait_for_high`](GpioPin::wait_for_high)): the first async wait on a
+    /// chip puts its fd into non-blocking mode for the chip's lifetime, which
+    /// makes this blocking `read` return `WouldBlock` instead of blocking.
+    pub fn wait_for_edge(&self, edge: Edge) -> io::Result<GpioEdgeEvent> {
+        let intr = match edge {
+            Edge::Rising => GPIO_INTR_EDGE_RISING,
+            Edge::Falling => GPIO_INTR_EDGE_FALLING,
+            Edge::Both => GPIO_INTR_EDGE_BOTH,
+        };
+        self.set_flags(GPIO_PIN_INPUT | intr)?;
+        loop {
+            let mut raw: gpio_event_summary = unsafe { std::mem::zeroed() };
+            let res = unsafe {
+                libc::read(
+                    self.chip.fd,
+                    &mut raw as *mut _ as *mut libc::c_void,
+                    std::mem::size_of::<gpio_event_summary>(),
+                )
+            };
+            if res == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            // The chip fd reports edges for every armed pin, not just this one.
+            if raw.gp_pin == self.num {
+                return Ok(GpioEdgeEvent {
+                    pin: raw.gp_pin,
+                    flags: raw.gp_pin_flags,
+                    first_time: timespec_to_duration(raw.gp_first_time),
+                    last_time: timespec_to_duration(raw.gp_last_time),
+                    count: raw.gp_count,
+                });
+            }
+        }
+    }
+}
+
+/// Borrows the chip's fd for `async-io` reactor registration without taking
+/// ownership of it; the `GpioChip` still owns and closes the real fd.
+#[cfg(feature = "async")]
+struct ChipFd(libc::c_int);
+
+#[cfg(feature = "async")]
+impl std::os::unix::io::AsRawFd for ChipFd {
+    fn as_raw_fd(&self) -> std::os::unix::io::RawFd {
+        self.0
+    }
+}
+
+#[cfg(feature = "async")]
+impl GpioChip {
+    /// The chip fd registered with the `async-io` reactor, creating and
+    /// caching the registration on first use. Registering the same raw fd
+    /// twice fails with `EEXIST`, so every pin on this chip shares one
+    /// registration instead of each wait call creating its own.
+    fn async_fd(&self) -> io::Result<&async_io::Async<ChipFd>> {
+        if let Some(async_fd) = self.async_fd.get() {
+            return Ok(async_fd);
+        }
+        let _guard = self.async_fd_init.lock().unwrap();
+        if let Some(async_fd) = self.async_fd.get() {
+            return Ok(async_fd);
+        }
+        let async_fd = async_io::Async::new(ChipFd(self.fd))?;
+        self.async_fd.set(async_fd).ok().expect("only ever set while holding async_fd_init");
+        Ok(self.async_fd.get().expect("just set above"))
+    }
+}
+
+// No published `embedded-hal-async` version's `Wait` trait is compatible with
+// the `embedded-hal` 1.0 alpha this crate otherwise builds against (it pins a
+// later alpha that dropped the `blocking` module these traits rely on), so
+// these are plain inherent methods rather than a trait impl.
+#[cfg(feature = "async")]
+impl<'c> GpioPin<'c, Input> {
+    /// Arm the pin for edge interrupt delivery and await a matching edge
+    /// becoming readable on the chip's fd, via `async-io`'s reactor, rather
+    /// than blocking the calling thread like [`wait_for_edge`](Self::wait_for_edge) does.
+    async fn wait_for_edge_async(&self, edge: Edge) -> io::Result<GpioEdgeEvent> {
+        let intr = match edge {
+            Edge::Rising => GPIO_INTR_EDGE_RISING,
+            Edge::Falling => GPIO_INTR_EDGE_FALLING,
+            Edge::Both => GPIO_INTR_EDGE_BOTH,
+        };
+        self.set_flags(GPIO_PIN_INPUT | intr)?;
+        let async_fd = self.chip.async_fd()?;
+        loop {
+            async_fd.readable().await?;
+            let mut raw: gpio_event_summary = unsafe { std::mem::zeroed() };
+            let res = unsafe {
+                libc::read(
+                    self.chip.fd,
+                    &mut raw as *mut _ as *mut libc::c_void,
+                    std::mem::size_of::<gpio_event_summary>(),
+                )
+            };
+            if res == -1 {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::WouldBlock {
+                    continue;
+                }
+                return Err(err);
+            }
+            // The chip fd reports edges for every armed pin, not just this one.
+            if raw.gp_pin == self.num {
+                return Ok(GpioEdgeEvent {
+                    pin: raw.gp_pin,
+                    flags: raw.gp_pin_flags,
+                    first_time: timespec_to_duration(raw.gp_first_time),
+                    last_time: timespec_to_duration(raw.gp_last_time),
+                    count: raw.gp_count,
+                });
+            }
+        }
+    }
+
+    pub async fn wait_for_high(&self) -> io::Result<()> {
+        use embedded_hal::digital::blocking::InputPin;
+        while !self.is_high()? {
+            self.wait_for_edge_async(Edge::Rising).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn wait_for_low(&self) -> io::Result<()> {
+        use embedded_hal::digital::blocking::InputPin;
+        while !self.is_low()? {
+            self.wait_for_edge_async(Edge::Falling).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn wait_for_rising_edge(&self) -> io::Result<()> {
+        self.wait_for_edge_async(Edge::Rising).await.map(|_| ())
+    }
+
+    pub async fn wait_for_falling_edge(&self) -> io::Result<()> {
+        self.wait_for_edge_async(Edge::Falling).await.map(|_| ())
+    }
+
+    pub async fn wait_for_any_edge(&self) -> io::Result<()> {
+        self.wait_for_edge_async(Edge::Both).await.map(|_| ())
+    }
+}
+
 impl<'c, const OM: u32> embedded_hal::digital::blocking::OutputPin for GpioPin<'c, Output<OM>> {
     type Error = io::Error;
 
     fn set_low(&mut self) -> Result<(), Self::Error> {
-        let res = unsafe { gpio_pin_set(self.chip.0, self.num, 0) };
+        let res = unsafe { gpio_pin_set(self.chip.fd, self.num, 0) };
         if res == -1 {
             return Err(io::Error::last_os_error());
         }
@@ -218,7 +432,7 @@ impl<'c, const OM: u32> embedded_hal::digital::blocking::OutputPin for GpioPin<'
     }
 
     fn set_high(&mut self) -> Result<(), Self::Error> {
-        let res = unsafe { gpio_pin_set(self.chip.0, self.num, 1) };
+        let res = unsafe { gpio_pin_set(self.chip.fd, self.num, 1) };
         if res == -1 {
             return Err(io::Error::last_os_error());
         }
@@ -230,7 +444,7 @@ impl<'c, const OM: u32> embedded_hal::digital::blocking::StatefulOutputPin
     for GpioPin<'c, Output<OM>>
 {
     fn is_set_high(&self) -> Result<bool, Self::Error> {
-        let res = unsafe { gpio_pin_get(self.chip.0, self.num) };
+        let res = unsafe { gpio_pin_get(self.chip.fd, self.num) };
         if res == -1 {
             return Err(io::Error::last_os_error());
         }
@@ -248,7 +462,63 @@ impl<'c, const OM: u32> embedded_hal::digital::blocking::ToggleableOutputPin
     type Error = io::Error;
 
     fn toggle(&mut self) -> Result<(), Self::Error> {
-        let res = unsafe { gpio_pin_toggle(self.chip.0, self.num) };
+        let res = unsafe { gpio_pin_toggle(self.chip.fd, self.num) };
+        if res == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+/// Implementations of the `embedded-hal` 0.2 digital traits for drivers that
+/// haven't moved to 1.0 yet, gated behind the `eh0` feature and delegating to
+/// the same `gpio_pin_*` plumbing as the traits above.
+#[cfg(feature = "eh0")]
+impl<'c> embedded_hal_0_2::digital::v2::InputPin for GpioPin<'c, Input> {
+    type Error = io::Error;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        let res = unsafe { gpio_pin_get(self.chip.fd, self.num) };
+        if res == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(res == 1)
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        self.is_high().map(|val| !val)
+    }
+}
+
+#[cfg(feature = "eh0")]
+impl<'c, const OM: u32> embedded_hal_0_2::digital::v2::OutputPin for GpioPin<'c, Output<OM>> {
+    type Error = io::Error;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        let res = unsafe { gpio_pin_set(self.chip.fd, self.num, 0) };
+        if res == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        let res = unsafe { gpio_pin_set(self.chip.fd, self.num, 1) };
+        if res == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "eh0")]
+impl<'c, const OM: u32> embedded_hal_0_2::digital::v2::ToggleableOutputPin
+    for GpioPin<'c, Output<OM>>
+{
+    type Error = io::Error;
+
+    fn toggle(&mut self) -> Result<(), Self::Error> {
+        let res = unsafe { gpio_pin_toggle(self.chip.fd, self.num) };
         if res == -1 {
             return Err(io::Error::last_os_error());
         }
@@ -262,6 +532,9 @@ const GPIO_PIN_OPENDRAIN: u32 = 0x04;
 const GPIO_PIN_PUSHPULL: u32 = 0x08;
 const GPIO_PIN_PRESET_LOW: u32 = 0x400;
 const GPIO_PIN_PRESET_HIGH: u32 = 0x800;
+const GPIO_INTR_EDGE_RISING: u32 = 0x10000;
+const GPIO_INTR_EDGE_FALLING: u32 = 0x20000;
+const GPIO_INTR_EDGE_BOTH: u32 = 0x30000;
 
 #[repr(C)]
 #[allow(non_camel_case_types)]
@@ -272,6 +545,20 @@ struct gpio_config_t {
     g_flags: u32,
 }
 
+#[repr(C)]
+#[allow(non_camel_case_types)]
+struct gpio_event_summary {
+    gp_pin: u32,
+    gp_pin_flags: u32,
+    gp_first_time: libc::timespec,
+    gp_last_time: libc::timespec,
+    gp_count: u32,
+}
+
+fn timespec_to_duration(ts: libc::timespec) -> std::time::Duration {
+    std::time::Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+}
+
 #[link(name = "gpio")]
 extern "C" {
     fn gpio_open(unit: libc::c_uint) -> libc::c_int;
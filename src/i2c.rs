@@ -7,67 +7,159 @@ use std::{
     fs::OpenOptions,
     io,
     os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd},
+    ptr,
+    time::Duration,
 };
 
-pub struct I2cBus(RawFd);
+pub struct I2cBus {
+    fd: RawFd,
+    retries: u8,
+    timeout: Option<Duration>,
+    validate_addresses: bool,
+}
 
-/// An i2c wrapper around std::io::Error.
+/// Why an iic(4) transfer aborted, decoded from the errno the kernel reports
+/// for the failing `I2CRDWR`/`rdwr` call.
 ///
-/// NOTE: values will be super wrong without https://reviews.freebsd.org/D33707
+/// This can only be as precise as the kernel is: before
+/// https://reviews.freebsd.org/D33707 most iic controller errors (including
+/// `IIC_ENOACK`/`IIC_ENOTACK`) are flattened to plain `EIO`, in which case
+/// [`I2cError::abort_reason`] returns `None` rather than guessing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AbortReason {
+    /// The address byte went unacknowledged (`IIC_ENOACK`).
+    AddressNack,
+    /// A data byte went unacknowledged (`IIC_ENOTACK`).
+    DataNack,
+    /// The controller reported a bus error, e.g. lost arbitration (`IIC_EBUSERR`).
+    BusError,
+    /// The bus was busy (`IIC_EBUSBSY`).
+    BusBusy,
+    /// The controller FIFO overflowed (`IIC_EOVERFLOW`).
+    Overflow,
+    /// The controller FIFO underflowed (`IIC_EUNDERFLOW`).
+    Underflow,
+    /// The transfer timed out (`IIC_ETIMEOUT`).
+    Timeout,
+}
+
+/// An i2c error, either a failed transfer or a rejected address.
 #[derive(Debug)]
-pub struct I2cError(io::Error);
+pub enum I2cError {
+    /// Wraps std::io::Error.
+    ///
+    /// NOTE: values will be super wrong without https://reviews.freebsd.org/D33707
+    Io(io::Error),
+    /// The address doesn't fit in 7 bits, see [`I2cBus::set_validate_addresses`].
+    AddressOutOfRange(u8),
+    /// The address is reserved by the I2C specification, see
+    /// [`I2cBus::set_validate_addresses`].
+    AddressReserved(u8),
+}
+
+impl I2cError {
+    /// The raw `errno` the kernel reported for the failing transfer, exposed
+    /// as-is so callers can inspect it even when [`I2cError::abort_reason`]
+    /// can't classify it. `None` for the address-validation variants, which
+    /// never reach the kernel.
+    pub fn raw_status(&self) -> Option<i32> {
+        match self {
+            I2cError::Io(err) => err.raw_os_error(),
+            I2cError::AddressOutOfRange(_) | I2cError::AddressReserved(_) => None,
+        }
+    }
+
+    /// Classify the failure by decoding the iic(4) status the kernel reported,
+    /// see [`AbortReason`]. Returns `None` on kernels that still flatten the
+    /// status to `EIO` (pre-D33707), and for the address-validation variants.
+    pub fn abort_reason(&self) -> Option<AbortReason> {
+        match self.raw_status() {
+            Some(libc::ENXIO) => Some(AbortReason::AddressNack),
+            Some(libc::ENOMSG) => Some(AbortReason::DataNack),
+            Some(libc::ECONNABORTED) => Some(AbortReason::BusError),
+            Some(libc::EBUSY) => Some(AbortReason::BusBusy),
+            Some(libc::EOVERFLOW) => Some(AbortReason::Overflow),
+            Some(libc::ENOBUFS) => Some(AbortReason::Underflow),
+            Some(libc::ETIMEDOUT) => Some(AbortReason::Timeout),
+            _ => None,
+        }
+    }
+}
 
 impl fmt::Display for I2cError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.0)
+        match self {
+            I2cError::Io(err) => write!(f, "{}", err),
+            I2cError::AddressOutOfRange(addr) => {
+                write!(f, "i2c address {:#04x} doesn't fit in 7 bits", addr)
+            },
+            I2cError::AddressReserved(addr) => {
+                write!(f, "i2c address {:#04x} is reserved", addr)
+            },
+        }
     }
 }
 
 impl From<io::Error> for I2cError {
     fn from(err: io::Error) -> I2cError {
-        I2cError(err)
+        I2cError::Io(err)
     }
 }
 
 impl error::Error for I2cError {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
-        Some(&self.0)
+        match self {
+            I2cError::Io(err) => Some(err),
+            I2cError::AddressOutOfRange(_) | I2cError::AddressReserved(_) => None,
+        }
     }
 }
 
 impl embedded_hal::i2c::Error for I2cError {
     fn kind(&self) -> embedded_hal::i2c::ErrorKind {
-        use embedded_hal::i2c::ErrorKind::*;
-        match self.0.raw_os_error() {
-            Some(libc::EALREADY) => Bus,
-            Some(libc::EOVERFLOW) => Overrun, // I guess
-            // Unfortunately both IIC_ENOACK and lots of other things translate to EIO
-            _ => Other,
+        use embedded_hal::i2c::{ErrorKind::*, NoAcknowledgeSource};
+        match self {
+            I2cError::AddressOutOfRange(_) | I2cError::AddressReserved(_) => Other,
+            I2cError::Io(_) => match self.abort_reason() {
+                Some(AbortReason::AddressNack) => NoAcknowledge(NoAcknowledgeSource::Address),
+                Some(AbortReason::DataNack) => NoAcknowledge(NoAcknowledgeSource::Data),
+                Some(AbortReason::BusError) => ArbitrationLoss,
+                Some(AbortReason::BusBusy) => Bus,
+                Some(AbortReason::Overflow) | Some(AbortReason::Underflow) => Overrun,
+                // embedded-hal has no Timeout variant; surface it through abort_reason() instead.
+                Some(AbortReason::Timeout) => Other,
+                None => match self.raw_status() {
+                    Some(libc::EALREADY) => Bus,
+                    // Unfortunately both IIC_ENOACK and lots of other things translate to EIO
+                    _ => Other,
+                },
+            },
         }
     }
 }
 
 impl FromRawFd for I2cBus {
     unsafe fn from_raw_fd(fd: RawFd) -> Self {
-        I2cBus(fd)
+        I2cBus { fd, retries: 0, timeout: None, validate_addresses: true }
     }
 }
 
 impl IntoRawFd for I2cBus {
     fn into_raw_fd(self) -> RawFd {
-        self.0
+        self.fd
     }
 }
 
 impl AsRawFd for I2cBus {
     fn as_raw_fd(&self) -> RawFd {
-        self.0
+        self.fd
     }
 }
 
 impl Drop for I2cBus {
     fn drop(&mut self) {
-        unsafe { libc::close(self.0) };
+        unsafe { libc::close(self.fd) };
     }
 }
 
@@ -81,24 +173,120 @@ impl I2cBus {
             .read(true)
             .write(true)
             .open(path)
-            .map(|f| I2cBus(f.into_raw_fd()))
+            .map(|f| I2cBus {
+                fd: f.into_raw_fd(),
+                retries: 0,
+                timeout: None,
+                validate_addresses: true,
+            })
             .map_err(|e| e.into())
     }
+
+    /// Reset the bus and renegotiate its clock speed via `I2CRSTCARD`.
+    pub fn set_frequency(&mut self, hz: u32) -> Result<(), I2cError> {
+        let speed = if hz <= 100_000 {
+            IIC_SLOW
+        } else if hz <= 400_000 {
+            IIC_FAST
+        } else {
+            IIC_FASTEST
+        };
+        let mut cmd = iiccmd { slave: 0, count: speed, last: 0, buf: ptr::null_mut() };
+        if unsafe { libc::ioctl(self.fd, I2CRSTCARD, &mut cmd as *mut _) } == -1 {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+
+    /// Set this host's own slave address via `I2CSADDR`, for when the
+    /// controller also has to answer as a slave.
+    pub fn set_own_address(&mut self, address: u16) -> Result<(), I2cError> {
+        let mut addr = address;
+        if unsafe { libc::ioctl(self.fd, I2CSADDR, &mut addr as *mut _) } == -1 {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+
+    /// Number of times to retry a transfer that fails with a transient bus
+    /// error (`ArbitrationLoss`/`Bus`) before giving up. iic(4) has no retry
+    /// count of its own, so this wraps `rdwr` in a software retry loop.
+    /// Defaults to 0 (no retries).
+    pub fn set_retries(&mut self, retries: u8) {
+        self.retries = retries;
+    }
+
+    /// Give up on a transfer, across all of its retries, after this long.
+    /// There's no per-transfer timeout ioctl either, so this just bounds the
+    /// retry loop above. Defaults to `None` (retry indefinitely, within
+    /// `retries`).
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Whether `read`/`write`/`write_read`/`exec` reject out-of-range or
+    /// reserved addresses before issuing a transfer, rather than letting the
+    /// kernel fail opaquely. Defaults to on; turn it off to issue
+    /// general-call (`0x00`) writes.
+    pub fn set_validate_addresses(&mut self, validate: bool) {
+        self.validate_addresses = validate;
+    }
+
+    fn check_address(&self, address: u8) -> Result<(), I2cError> {
+        if !self.validate_addresses {
+            return Ok(());
+        }
+        if address >= 0x80 {
+            return Err(I2cError::AddressOutOfRange(address));
+        }
+        if address <= 0x07 || address >= 0x78 {
+            return Err(I2cError::AddressReserved(address));
+        }
+        Ok(())
+    }
+
+    fn rdwr_retrying(&self, msgs: &[iic_msg]) -> Result<(), I2cError> {
+        let deadline = self.timeout.map(|t| std::time::Instant::now() + t);
+        let mut attempt = 0;
+        loop {
+            match rdwr(self.fd, msgs) {
+                Ok(()) => return Ok(()),
+                Err(err)
+                    if attempt < self.retries
+                        && !past_deadline(deadline)
+                        && is_transient(&err) =>
+                {
+                    attempt += 1;
+                    continue;
+                },
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+fn past_deadline(deadline: Option<std::time::Instant>) -> bool {
+    matches!(deadline, Some(d) if std::time::Instant::now() >= d)
+}
+
+/// Whether a failed transfer is worth retrying, i.e. a transient bus error
+/// rather than a permanent one like an absent device's `NoAcknowledge`.
+fn is_transient(err: &I2cError) -> bool {
+    use embedded_hal::i2c::{Error, ErrorKind};
+    matches!(err.kind(), ErrorKind::ArbitrationLoss | ErrorKind::Bus)
 }
 
 impl embedded_hal::i2c::blocking::Read for I2cBus {
     type Error = I2cError;
 
     fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
-        rdwr(
-            self.0,
-            &[iic_msg {
-                addr: (address as u16) << 1,
-                flags: IIC_M_RD,
-                len: buffer.len() as u16,
-                buf: buffer as *const _ as *mut _,
-            }],
-        )
+        self.check_address(address)?;
+        self.rdwr_retrying(&[iic_msg {
+            addr: (address as u16) << 1,
+            flags: IIC_M_RD,
+            len: buffer.len() as u16,
+            buf: buffer as *const _ as *mut _,
+        }])
     }
 }
 
@@ -106,15 +294,13 @@ impl embedded_hal::i2c::blocking::Write for I2cBus {
     type Error = I2cError;
 
     fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
-        rdwr(
-            self.0,
-            &[iic_msg {
-                addr: (address as u16) << 1,
-                flags: IIC_M_WR,
-                len: bytes.len() as u16,
-                buf: bytes as *const _ as *mut _,
-            }],
-        )
+        self.check_address(address)?;
+        self.rdwr_retrying(&[iic_msg {
+            addr: (address as u16) << 1,
+            flags: IIC_M_WR,
+            len: bytes.len() as u16,
+            buf: bytes as *const _ as *mut _,
+        }])
     }
 }
 
@@ -126,7 +312,7 @@ impl embedded_hal::i2c::blocking::WriteIter for I2cBus {
         B: IntoIterator<Item = u8>,
     {
         use embedded_hal::i2c::blocking::Write;
-        self.write(address, &mut bytes.into_iter().collect::<Vec<_>>())
+        self.write(address, &bytes.into_iter().collect::<Vec<_>>())
     }
 }
 
@@ -139,23 +325,21 @@ impl embedded_hal::i2c::blocking::WriteRead for I2cBus {
         bytes: &[u8],
         buffer: &mut [u8],
     ) -> Result<(), Self::Error> {
-        rdwr(
-            self.0,
-            &[
-                iic_msg {
-                    addr: (address as u16) << 1,
-                    flags: IIC_M_WR | IIC_M_NOSTOP,
-                    len: bytes.len() as u16,
-                    buf: bytes as *const _ as *mut _,
-                },
-                iic_msg {
-                    addr: (address as u16) << 1,
-                    flags: IIC_M_RD,
-                    len: buffer.len() as u16,
-                    buf: buffer as *const _ as *mut _,
-                },
-            ],
-        )
+        self.check_address(address)?;
+        self.rdwr_retrying(&[
+            iic_msg {
+                addr: (address as u16) << 1,
+                flags: IIC_M_WR | IIC_M_NOSTOP,
+                len: bytes.len() as u16,
+                buf: bytes as *const _ as *mut _,
+            },
+            iic_msg {
+                addr: (address as u16) << 1,
+                flags: IIC_M_RD,
+                len: buffer.len() as u16,
+                buf: buffer as *const _ as *mut _,
+            },
+        ])
     }
 }
 
@@ -172,7 +356,7 @@ impl embedded_hal::i2c::blocking::WriteIterRead for I2cBus {
         B: IntoIterator<Item = u8>,
     {
         use embedded_hal::i2c::blocking::WriteRead;
-        self.write_read(address, &mut bytes.into_iter().collect::<Vec<_>>(), buffer)
+        self.write_read(address, &bytes.into_iter().collect::<Vec<_>>(), buffer)
     }
 }
 
@@ -191,9 +375,10 @@ impl embedded_hal::i2c::blocking::Transactional for I2cBus {
         address: u8,
         operations: &mut [embedded_hal::i2c::blocking::Operation<'a>],
     ) -> Result<(), Self::Error> {
+        self.check_address(address)?;
         let mut st = OpState::First;
         let mut msgs = Vec::with_capacity(operations.len());
-        let mut it = operations.into_iter().peekable();
+        let mut it = operations.iter_mut().peekable();
 
         while let Some(op) = it.next() {
             use embedded_hal::i2c::blocking::Operation;
@@ -226,7 +411,7 @@ impl embedded_hal::i2c::blocking::Transactional for I2cBus {
             });
         }
 
-        rdwr(self.0, &msgs[..])
+        self.rdwr_retrying(&msgs[..])
     }
 }
 
@@ -246,11 +431,76 @@ impl embedded_hal::i2c::blocking::TransactionalIter for I2cBus {
     }
 }
 
+/// Implementations of the `embedded-hal` 0.2 I2C traits for drivers that haven't
+/// moved to 1.0 yet, gated behind the `eh0` feature and delegating to the same
+/// `rdwr` plumbing as the traits above.
+#[cfg(feature = "eh0")]
+impl embedded_hal_0_2::blocking::i2c::Read for I2cBus {
+    type Error = I2cError;
+
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.check_address(address)?;
+        self.rdwr_retrying(&[iic_msg {
+            addr: (address as u16) << 1,
+            flags: IIC_M_RD,
+            len: buffer.len() as u16,
+            buf: buffer as *const _ as *mut _,
+        }])
+    }
+}
+
+#[cfg(feature = "eh0")]
+impl embedded_hal_0_2::blocking::i2c::Write for I2cBus {
+    type Error = I2cError;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.check_address(address)?;
+        self.rdwr_retrying(&[iic_msg {
+            addr: (address as u16) << 1,
+            flags: IIC_M_WR,
+            len: bytes.len() as u16,
+            buf: bytes as *const _ as *mut _,
+        }])
+    }
+}
+
+#[cfg(feature = "eh0")]
+impl embedded_hal_0_2::blocking::i2c::WriteRead for I2cBus {
+    type Error = I2cError;
+
+    fn write_read(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        self.check_address(address)?;
+        self.rdwr_retrying(&[
+            iic_msg {
+                addr: (address as u16) << 1,
+                flags: IIC_M_WR | IIC_M_NOSTOP,
+                len: bytes.len() as u16,
+                buf: bytes as *const _ as *mut _,
+            },
+            iic_msg {
+                addr: (address as u16) << 1,
+                flags: IIC_M_RD,
+                len: buffer.len() as u16,
+                buf: buffer as *const _ as *mut _,
+            },
+        ])
+    }
+}
+
 const IIC_M_WR: u16 = 0x00;
 const IIC_M_RD: u16 = 0x01;
 const IIC_M_NOSTOP: u16 = 0x02;
 const IIC_M_NOSTART: u16 = 0x04;
 
+const IIC_SLOW: libc::c_int = 1;
+const IIC_FAST: libc::c_int = 2;
+const IIC_FASTEST: libc::c_int = 3;
+
 #[repr(C)]
 #[allow(non_camel_case_types)]
 struct iic_msg {
@@ -267,13 +517,25 @@ struct iic_rdwr_data {
     nmsgs: u32,
 }
 
+#[repr(C)]
+#[allow(non_camel_case_types)]
+struct iiccmd {
+    slave: libc::c_uchar,
+    count: libc::c_int,
+    last: libc::c_int,
+    buf: *mut libc::c_uchar,
+}
+
+const I2CRSTCARD: libc::c_ulong = 0x80186903; // _IOW('i', 3, struct iiccmd)
+const I2CSADDR: libc::c_ulong = 0x80026907; // _IOW('i', 7, i2c_addr_t)
+
 fn rdwr(fd: RawFd, msgs: &[iic_msg]) -> Result<(), I2cError> {
     let mut dat = iic_rdwr_data { msgs: msgs.as_ptr(), nmsgs: msgs.len() as u32 };
     let res = unsafe {
         libc::ioctl(fd, 0x80106906 /*I2CRDWR*/, &mut dat as *mut _)
     };
     if res == -1 {
-        return Err(I2cError(io::Error::last_os_error()));
+        return Err(io::Error::last_os_error().into());
     }
     Ok(())
 }
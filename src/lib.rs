@@ -7,3 +7,6 @@ pub use gpio::GpioChip;
 
 pub mod i2c;
 pub use i2c::I2cBus;
+
+pub mod spi;
+pub use spi::SpiDevice;
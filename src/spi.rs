@@ -0,0 +1,240 @@
+//! Implementation of [`embedded-hal`] SPI traits using the FreeBSD spigen(4) device interface
+//!
+//! [`embedded-hal`]: https://docs.rs/embedded-hal
+
+use std::{
+    error, fmt,
+    fs::OpenOptions,
+    io,
+    os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd},
+};
+
+pub struct SpiDevice(RawFd);
+
+/// An spi wrapper around std::io::Error.
+#[derive(Debug)]
+pub struct SpiError(io::Error);
+
+impl fmt::Display for SpiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<io::Error> for SpiError {
+    fn from(err: io::Error) -> SpiError {
+        SpiError(err)
+    }
+}
+
+impl error::Error for SpiError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+impl embedded_hal::spi::Error for SpiError {
+    fn kind(&self) -> embedded_hal::spi::ErrorKind {
+        embedded_hal::spi::ErrorKind::Other
+    }
+}
+
+/// Configuration applied to a [`SpiDevice`] when it's opened.
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    pub mode: embedded_hal::spi::Mode,
+    pub clock_hz: u32,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config { mode: embedded_hal::spi::MODE_0, clock_hz: 1_000_000 }
+    }
+}
+
+impl FromRawFd for SpiDevice {
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        SpiDevice(fd)
+    }
+}
+
+impl IntoRawFd for SpiDevice {
+    fn into_raw_fd(self) -> RawFd {
+        self.0
+    }
+}
+
+impl AsRawFd for SpiDevice {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0
+    }
+}
+
+impl Drop for SpiDevice {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.0) };
+    }
+}
+
+impl SpiDevice {
+    pub fn from_unit(unit: u32, chip_select: u32, config: Config) -> Result<SpiDevice, SpiError> {
+        Self::from_path(format!("/dev/spigen{}.{}", unit, chip_select), config)
+    }
+
+    pub fn from_path<P: AsRef<std::path::Path>>(
+        path: P,
+        config: Config,
+    ) -> Result<SpiDevice, SpiError> {
+        let mut dev = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .map(|f| SpiDevice(f.into_raw_fd()))?;
+        dev.configure(config)?;
+        Ok(dev)
+    }
+
+    /// Apply a [`Config`] to an already-open device.
+    pub fn configure(&mut self, config: Config) -> Result<(), SpiError> {
+        self.set_mode(config.mode)?;
+        self.set_clock_hz(config.clock_hz)?;
+        Ok(())
+    }
+
+    pub fn mode(&self) -> Result<embedded_hal::spi::Mode, SpiError> {
+        let mut raw: u32 = 0;
+        if unsafe { libc::ioctl(self.0, SPIGENIOC_GET_SPI_MODE, &mut raw as *mut _) } == -1 {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(mode_from_raw(raw))
+    }
+
+    pub fn set_mode(&mut self, mode: embedded_hal::spi::Mode) -> Result<(), SpiError> {
+        let mut raw = mode_to_raw(mode);
+        if unsafe { libc::ioctl(self.0, SPIGENIOC_SET_SPI_MODE, &mut raw as *mut _) } == -1 {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+
+    pub fn clock_hz(&self) -> Result<u32, SpiError> {
+        let mut hz: u32 = 0;
+        if unsafe { libc::ioctl(self.0, SPIGENIOC_GET_CLOCK_SPEED, &mut hz as *mut _) } == -1 {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(hz)
+    }
+
+    pub fn set_clock_hz(&mut self, hz: u32) -> Result<(), SpiError> {
+        let mut hz = hz;
+        if unsafe { libc::ioctl(self.0, SPIGENIOC_SET_CLOCK_SPEED, &mut hz as *mut _) } == -1 {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+}
+
+fn mode_to_raw(mode: embedded_hal::spi::Mode) -> u32 {
+    use embedded_hal::spi::{Phase, Polarity};
+    let cpol = mode.polarity == Polarity::IdleHigh;
+    let cpha = mode.phase == Phase::CaptureOnSecondTransition;
+    ((cpol as u32) << 1) | (cpha as u32)
+}
+
+fn mode_from_raw(raw: u32) -> embedded_hal::spi::Mode {
+    use embedded_hal::spi::{Mode, Phase, Polarity};
+    Mode {
+        polarity: if raw & 0b10 != 0 { Polarity::IdleHigh } else { Polarity::IdleLow },
+        phase: if raw & 0b01 != 0 {
+            Phase::CaptureOnSecondTransition
+        } else {
+            Phase::CaptureOnFirstTransition
+        },
+    }
+}
+
+impl embedded_hal::spi::blocking::Write<u8> for SpiDevice {
+    type Error = SpiError;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        transfer(self.0, words, &mut [])
+    }
+}
+
+impl embedded_hal::spi::blocking::Read<u8> for SpiDevice {
+    type Error = SpiError;
+
+    fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        transfer(self.0, &[], words)
+    }
+}
+
+impl embedded_hal::spi::blocking::Transfer<u8> for SpiDevice {
+    type Error = SpiError;
+
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        // embedded-hal allows read and write to differ in length: clock out
+        // `write` (zero-padded) for max(read, write) words and hand back as
+        // much of the response as `read` has room for.
+        let len = read.len().max(write.len());
+        let mut buf = vec![0u8; len];
+        buf[..write.len()].copy_from_slice(write);
+        transfer(self.0, &[], &mut buf)?;
+        let n = read.len().min(len);
+        read[..n].copy_from_slice(&buf[..n]);
+        Ok(())
+    }
+}
+
+impl embedded_hal::spi::blocking::TransferInplace<u8> for SpiDevice {
+    type Error = SpiError;
+
+    fn transfer_inplace(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        transfer(self.0, &[], words)
+    }
+}
+
+// embedded-hal 1.0-alpha has no `spi::blocking::WriteRead` trait (only
+// `i2c::blocking::WriteRead`), so this is an inherent method instead.
+impl SpiDevice {
+    pub fn write_read(&mut self, write: &[u8], read: &mut [u8]) -> Result<(), SpiError> {
+        transfer(self.0, write, read)
+    }
+}
+
+#[repr(C)]
+#[allow(non_camel_case_types)]
+struct spigen_iovec {
+    iov_base: *mut libc::c_void,
+    iov_len: libc::size_t,
+}
+
+#[repr(C)]
+#[allow(non_camel_case_types)]
+struct spigen_transfer {
+    st_command: spigen_iovec,
+    st_data: spigen_iovec,
+}
+
+// st_command is clocked out first (rx discarded), st_data is clocked out and
+// simultaneously overwritten with the received bytes (full duplex, in place).
+fn transfer(fd: RawFd, command: &[u8], data: &mut [u8]) -> Result<(), SpiError> {
+    let mut xfer = spigen_transfer {
+        st_command: spigen_iovec {
+            iov_base: command.as_ptr() as *mut _,
+            iov_len: command.len(),
+        },
+        st_data: spigen_iovec { iov_base: data.as_mut_ptr() as *mut _, iov_len: data.len() },
+    };
+    let res = unsafe { libc::ioctl(fd, SPIGENIOC_TRANSFER, &mut xfer as *mut _) };
+    if res == -1 {
+        return Err(io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+const SPIGENIOC_TRANSFER: libc::c_ulong = 0xc0205300; // _IOWR('S', 0, struct spigen_transfer)
+const SPIGENIOC_GET_CLOCK_SPEED: libc::c_ulong = 0x40045301; // _IOR('S', 1, u32)
+const SPIGENIOC_SET_CLOCK_SPEED: libc::c_ulong = 0x80045302; // _IOW('S', 2, u32)
+const SPIGENIOC_GET_SPI_MODE: libc::c_ulong = 0x40045303; // _IOR('S', 3, u32)
+const SPIGENIOC_SET_SPI_MODE: libc::c_ulong = 0x80045304; // _IOW('S', 4, u32)